@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use rocket::fairing::{AdHoc, Fairing};
 use rocket::handler;
@@ -15,13 +18,81 @@ use crate::OAuthConfig;
 
 const STATE_COOKIE_NAME: &str = "rocket_oauth2_state";
 
+/// Name of the private cookie holding the PKCE code verifier generated by
+/// `get_redirect`, kept separate from [`STATE_COOKIE_NAME`] since the
+/// adapter-generated `state` value isn't guaranteed to avoid any particular
+/// delimiter character.
+const PKCE_VERIFIER_COOKIE_NAME: &str = "rocket_oauth2_pkce_verifier";
+
+/// Conventional name of a private cookie an application may use to stash the
+/// current refresh token. If present, the optional logout route mounted via
+/// [`OAuth2::custom_with_logout`] will best-effort revoke it.
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "rocket_oauth2_refresh";
+
+/// The out-of-band redirect URI for clients that cannot host a web callback,
+/// e.g. CLI or desktop apps. Set the [`OAuthConfig`]'s `redirect_uri` to this
+/// (or a provider-specific OOB/webchannel variant), then use
+/// [`OAuth2::custom_oob`] instead of [`OAuth2::custom`] so no callback route
+/// is mounted.
+pub const OOB_REDIRECT_URI: &str = "urn:ietf:wg:oauth:2.0:oob";
+
+/// Characters allowed in a PKCE code verifier, per RFC 7636 §4.1
+/// (`[A-Z] [a-z] [0-9] "-" "." "_" "~"`).
+const PKCE_VERIFIER_CHARSET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+
+/// Length of the generated code verifier. RFC 7636 §4.1 allows 43-128
+/// characters; 64 gives a comfortable margin of entropy.
+const PKCE_VERIFIER_LEN: usize = 64;
+
+/// Generate a cryptographically random PKCE code verifier as described in
+/// RFC 7636 §4.1.
+fn generate_pkce_verifier() -> String {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    (0..PKCE_VERIFIER_LEN)
+        .map(|_| PKCE_VERIFIER_CHARSET[rng.gen_range(0, PKCE_VERIFIER_CHARSET.len())] as char)
+        .collect()
+}
+
+/// Compute the `S256` PKCE code challenge for a given verifier, per RFC 7636
+/// §4.2: `BASE64URL-ENCODE(SHA256(verifier))`, without padding.
+fn pkce_code_challenge(verifier: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(verifier.as_bytes());
+    base64::encode_config(&digest, base64::URL_SAFE_NO_PAD)
+}
+
 /// The token types which can be exchanged with the token endpoint
 #[derive(Clone, PartialEq, Debug)]
 pub enum TokenRequest {
     /// Used for the Authorization Code exchange
-    AuthorizationCode(String),
+    AuthorizationCode {
+        /// The authorization code returned by the provider.
+        code: String,
+        /// The PKCE code verifier generated at redirect time, carried through
+        /// so the adapter can include it in the token request as described in
+        /// RFC 7636 §4.5. `None` if PKCE is not in use for this flow.
+        pkce_verifier: Option<String>,
+    },
     /// Used to refresh an access token
-    RefreshToken(String)
+    RefreshToken(String),
+    /// Used to identify an access token. Revocation (RFC 7009) does not use
+    /// this variant; [`OAuth2::revoke`] takes a [`RevocableToken`].
+    AccessToken(String),
+}
+
+/// A token to be revoked with [`OAuth2::revoke`], as described by RFC 7009.
+/// Unlike [`TokenRequest`], this has no `AuthorizationCode` variant: an
+/// authorization code isn't a token and revoking one is meaningless, so the
+/// type system rules it out rather than leaving every [`Adapter`] impl to
+/// guard against it at runtime.
+#[derive(Clone, PartialEq, Debug)]
+pub enum RevocableToken {
+    /// An access token, to be revoked with `token_type_hint=access_token`.
+    AccessToken(String),
+    /// A refresh token, to be revoked with `token_type_hint=refresh_token`.
+    RefreshToken(String),
 }
 
 /// The server's response to a successful token exchange, defined in
@@ -48,6 +119,147 @@ pub struct TokenResponse {
     pub extras: HashMap<String, JsonValue>,
 }
 
+/// Default threshold, in seconds, before an access token's expiry at which
+/// [`OAuth2::get_valid_token`] will proactively refresh it.
+pub const DEFAULT_REFRESH_THRESHOLD_SECS: u64 = 60;
+
+/// A [`TokenResponse`] paired with the wall-clock time its access token
+/// expires at. `expires_in` is only meaningful at the moment a token is
+/// issued, so it is converted to an absolute `SystemTime` before caching
+/// (rather than e.g. an `Instant`, which is tied to an arbitrary
+/// per-process reference point and can't be persisted across a restart) to
+/// stay meaningful while cached.
+#[derive(Clone, Debug)]
+pub struct StoredToken {
+    /// The cached token.
+    pub token: TokenResponse,
+    /// The wall-clock time the cached `access_token` expires, or `None` if
+    /// the provider did not report an `expires_in`. When `None`,
+    /// [`OAuth2::get_valid_token`] has no expiry to compare against and
+    /// trusts the cached token indefinitely; if the resource server later
+    /// rejects it anyway (e.g. a `401` from revocation out-of-band), use
+    /// [`OAuth2::force_refresh_token`] to obtain a new one.
+    pub expires_at: Option<SystemTime>,
+}
+
+/// A `TokenStore` caches [`StoredToken`]s keyed by an application-chosen
+/// identity `K` (e.g. a user id), so [`OAuth2::get_valid_token`] can avoid
+/// refreshing a token that is still valid.
+pub trait TokenStore<K>: Send + Sync + 'static {
+    /// Look up the cached token for `key`, if any.
+    fn get(&self, key: &K) -> Option<StoredToken>;
+
+    /// Store `token` for `key`, replacing any previous entry.
+    fn put(&self, key: K, token: StoredToken);
+
+    /// Run `f` with exclusive access to `key`'s entry. [`OAuth2::get_valid_token_with_threshold`]
+    /// runs its get-then-maybe-refresh-then-put sequence entirely inside
+    /// `f`, so two concurrent calls for the same key can't both see the same
+    /// stale token and both refresh with the same (often single-use)
+    /// refresh token.
+    fn with_lock<R>(&self, key: &K, f: impl FnOnce() -> R) -> R;
+}
+
+/// An in-memory [`TokenStore`], guarded by a `Mutex`. Suitable as a default
+/// for single-process deployments; applications with multiple processes or
+/// that need persistence should provide their own `TokenStore`.
+#[derive(Debug)]
+pub struct MemoryTokenStore<K> {
+    tokens: Mutex<HashMap<K, StoredToken>>,
+    // Per-key locks used by `with_lock`. Entries are never removed, trading a
+    // slow unbounded-key-space leak for simplicity; fine for the bounded set
+    // of users/clients this is meant to cache tokens for.
+    locks: Mutex<HashMap<K, Arc<Mutex<()>>>>,
+}
+
+impl<K> MemoryTokenStore<K> {
+    /// Create an empty `MemoryTokenStore`.
+    pub fn new() -> Self {
+        MemoryTokenStore {
+            tokens: Mutex::new(HashMap::new()),
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K> Default for MemoryTokenStore<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send + Sync + 'static> TokenStore<K> for MemoryTokenStore<K> {
+    fn get(&self, key: &K) -> Option<StoredToken> {
+        self.tokens
+            .lock()
+            .expect("token store mutex poisoned")
+            .get(key)
+            .cloned()
+    }
+
+    fn put(&self, key: K, token: StoredToken) {
+        self.tokens
+            .lock()
+            .expect("token store mutex poisoned")
+            .insert(key, token);
+    }
+
+    fn with_lock<R>(&self, key: &K, f: impl FnOnce() -> R) -> R {
+        let key_lock = {
+            let mut locks = self.locks.lock().expect("token store mutex poisoned");
+            locks
+                .entry(key.clone())
+                .or_insert_with(|| Arc::new(Mutex::new(())))
+                .clone()
+        };
+        let _guard = key_lock.lock().expect("token store mutex poisoned");
+        f()
+    }
+}
+
+/// The error type returned by [`OAuth2::get_valid_token`].
+#[derive(Debug)]
+pub enum TokenStoreError<E> {
+    /// No token has been cached for the given key; the application must
+    /// complete the OAuth flow (or otherwise seed the [`TokenStore`]) first.
+    NotFound,
+    /// The cached token needs refreshing (it expires within the refresh
+    /// threshold, has already expired, or a refresh was forced) and carries
+    /// no refresh token, so it cannot be renewed.
+    NoRefreshToken,
+    /// Refreshing the token failed.
+    Adapter(E),
+}
+
+/// The result of a token introspection request, as described in RFC 7662 §2.2.
+#[derive(Clone, PartialEq, Debug)]
+#[derive(serde::Deserialize)]
+pub struct IntrospectionResponse {
+    /// Whether or not the presented token is currently active.
+    pub active: bool,
+    /// The scopes associated with the token, if the server returned them.
+    pub scope: Option<String>,
+    /// The client the token was issued to, if the server returned it.
+    pub client_id: Option<String>,
+    /// The username of the resource owner who authorized the token, if the
+    /// server returned it.
+    pub username: Option<String>,
+    /// The type of token, e.g. `access_token` or `refresh_token`.
+    pub token_type: Option<String>,
+    /// The token's expiration time, as seconds since the epoch, if provided.
+    pub exp: Option<i64>,
+    /// The time the token was issued, as seconds since the epoch, if provided.
+    pub iat: Option<i64>,
+    /// The subject the token was issued for, if provided.
+    pub sub: Option<String>,
+    /// The intended audience for the token, if provided.
+    pub aud: Option<String>,
+
+    /// Additional values returned by the authorization server, if any.
+    #[serde(flatten)]
+    pub extras: HashMap<String, JsonValue>,
+}
+
 /// An OAuth2 `Adapater` can be implemented by any type that facilitates the
 /// Authorization Code Grant as described in RFC 6749 §4.1. The implementing
 /// type must be able to generate an authorization URI and perform the token
@@ -58,16 +270,48 @@ pub trait Adapter: Send + Sync + 'static {
     type Error: Debug;
 
     /// Generate an authorization URI and state value as described by RFC 6749 §4.1.1.
+    ///
+    /// If `pkce_challenge` is `Some((code_challenge, code_challenge_method))`, the
+    /// adapter must append `code_challenge` and `code_challenge_method` query
+    /// parameters as described in RFC 7636 §4.3.
     fn authorization_uri(
         &self,
         config: &OAuthConfig,
         scopes: &[&str],
+        pkce_challenge: Option<(&str, &str)>,
     ) -> Result<(Absolute<'static>, String), Self::Error>;
 
     /// Perform the token exchange in accordance with RFC 6749 §4.1.3 given the
     /// authorization code provided by the service.
     fn exchange_code(&self, config: &OAuthConfig, token: TokenRequest)
         -> Result<TokenResponse, Self::Error>;
+
+    /// Query the authorization server about the current state of `token`, as
+    /// described by RFC 7662. A typical implementation POSTs
+    /// `token=<token>&token_type_hint=access_token` (form-encoded, with client
+    /// authentication) to a configured introspection endpoint.
+    fn introspect(&self, config: &OAuthConfig, token: &str) -> Result<IntrospectionResponse, Self::Error>;
+
+    /// Revoke `token` so it can no longer be used, as described by RFC 7009.
+    /// A typical implementation POSTs `token=<token>&token_type_hint=<hint>`
+    /// (form-encoded, with client authentication) to a configured revocation
+    /// endpoint.
+    fn revoke(&self, config: &OAuthConfig, token: RevocableToken) -> Result<(), Self::Error>;
+}
+
+/// The error response a provider sends to the callback URI when the user
+/// denies access or the authorization request is otherwise rejected, as
+/// described in RFC 6749 §4.1.2.1.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TokenError {
+    /// A single ASCII error code, e.g. `access_denied` or `invalid_scope`.
+    pub error: String,
+    /// Additional human-readable text providing more information about the
+    /// error, if the provider sent one.
+    pub error_description: Option<String>,
+    /// A URI identifying a human-readable web page with information about the
+    /// error, if the provider sent one.
+    pub error_uri: Option<String>,
 }
 
 /// An OAuth2 `Callback` implements application-specific OAuth client logic,
@@ -84,6 +328,20 @@ pub trait Callback: Send + Sync + 'static {
     /// Implementors should perform application-specific logic here, such as
     /// checking a database or setting a login cookie.
     fn callback(&self, request: &Request<'_>, token: TokenResponse) -> Self::Responder;
+
+    /// This method will be called when the provider redirects back with an
+    /// error instead of an authorization code (RFC 6749 §4.1.2.1), e.g.
+    /// because the user denied access. The default implementation logs the
+    /// error and responds with `400 Bad Request`, preserving the library's
+    /// previous behavior; override it to show a friendlier page or redirect.
+    fn callback_error(
+        &self,
+        _request: &Request<'_>,
+        error: TokenError,
+    ) -> Result<Self::Responder, Status> {
+        log::error!("Provider returned an error: {:?}", error);
+        Err(Status::BadRequest)
+    }
 }
 
 impl<F, R> Callback for F
@@ -98,6 +356,31 @@ where
     }
 }
 
+/// Whether a cached token should be refreshed before being returned, given
+/// its recorded expiry (if any), the configured refresh threshold, and
+/// whether a refresh was explicitly forced (see [`OAuth2::force_refresh_token`]).
+fn needs_refresh(expires_at: Option<SystemTime>, threshold: Duration, force: bool) -> bool {
+    force
+        || match expires_at {
+            Some(expires_at) => match expires_at.duration_since(SystemTime::now()) {
+                Ok(remaining) => remaining <= threshold,
+                // `expires_at` is already in the past.
+                Err(_) => true,
+            },
+            None => false,
+        }
+}
+
+/// Apply the RFC 6749 §6 rule that a refresh response omitting
+/// `refresh_token` means the client must keep using the previous one.
+/// Without this, the next refresh would find no refresh token cached and
+/// fail permanently even though the original is still valid.
+fn carry_forward_refresh_token(new_token: &mut TokenResponse, previous_refresh_token: Option<String>) {
+    if new_token.refresh_token.is_none() {
+        new_token.refresh_token = previous_refresh_token;
+    }
+}
+
 /// The `OAuth2` structure implements OAuth in a Rocket application by setting
 /// up OAuth-related route handlers.
 ///
@@ -117,6 +400,8 @@ pub struct OAuth2<A, C> {
     callback: C,
     config: OAuthConfig,
     login_scopes: Vec<String>,
+    pkce_enabled: bool,
+    logout_redirect: Option<String>,
 }
 
 impl<A: Adapter, C: Callback> OAuth2<A, C> {
@@ -164,12 +449,50 @@ impl<A: Adapter, C: Callback> OAuth2<A, C> {
     /// place an instance of `OAuth2<A, C>` in managed state and mount a
     /// redirect handler. It will also mount a login handler if `login` is
     /// `Some`.
+    ///
+    /// PKCE (RFC 7636) is enabled by default; use [`OAuth2::custom_with_pkce`]
+    /// if a provider rejects the extra `code_challenge`/`code_challenge_method`
+    /// parameters.
     pub fn custom(
         adapter: A,
         callback: C,
         config: OAuthConfig,
         callback_uri: &str,
         login: Option<(&str, Vec<String>)>,
+    ) -> impl Fairing {
+        Self::custom_with_pkce(adapter, callback, config, callback_uri, login, true)
+    }
+
+    /// Like [`OAuth2::custom`], but allows explicitly enabling or disabling
+    /// PKCE (RFC 7636). Some providers reject the `code_challenge` and
+    /// `code_challenge_method` query parameters, so PKCE can be turned off
+    /// for those; it defaults to on everywhere else.
+    pub fn custom_with_pkce(
+        adapter: A,
+        callback: C,
+        config: OAuthConfig,
+        callback_uri: &str,
+        login: Option<(&str, Vec<String>)>,
+        pkce_enabled: bool,
+    ) -> impl Fairing {
+        Self::custom_with_logout(adapter, callback, config, callback_uri, login, pkce_enabled, None)
+    }
+
+    /// Like [`OAuth2::custom_with_pkce`], but also mounts a logout route if
+    /// `logout` is `Some((logout_uri, post_logout_redirect_uri))`. The logout
+    /// route best-effort revokes the refresh token stashed in the
+    /// [`REFRESH_TOKEN_COOKIE_NAME`] private cookie (if the application set
+    /// one), clears that cookie, and redirects to `post_logout_redirect_uri`.
+    /// Applications remain responsible for clearing their own session/login
+    /// cookies, typically by also handling `logout_uri` themselves upstream.
+    pub fn custom_with_logout(
+        adapter: A,
+        callback: C,
+        config: OAuthConfig,
+        callback_uri: &str,
+        login: Option<(&str, Vec<String>)>,
+        pkce_enabled: bool,
+        logout: Option<(&str, &str)>,
     ) -> impl Fairing {
         let mut routes = Vec::new();
 
@@ -185,11 +508,62 @@ impl<A: Adapter, C: Callback> OAuth2<A, C> {
             login_scopes = scopes;
         }
 
+        let mut logout_redirect = None;
+        if let Some((uri, redirect_uri)) = logout {
+            routes.push(Route::new(Method::Get, uri, logout_handler::<A, C>));
+            logout_redirect = Some(redirect_uri.to_string());
+        }
+
+        let oauth2 = Self {
+            adapter,
+            callback,
+            config,
+            login_scopes,
+            pkce_enabled,
+            logout_redirect,
+        };
+
+        AdHoc::on_attach("OAuth Mount", |rocket| {
+            Ok(rocket.manage(oauth2).mount("/", routes))
+        })
+    }
+
+    /// Returns an OAuth2 fairing configured for an out-of-band flow: no
+    /// callback route is mounted, since there is no hosted redirect target
+    /// for the provider to reach. `config`'s `redirect_uri` should be set to
+    /// [`OOB_REDIRECT_URI`] (or a provider-specific variant). After
+    /// [`OAuth2::get_redirect`] sends the user to the provider, the
+    /// application obtains the resulting authorization code out-of-band (the
+    /// user pastes it in, a custom URI scheme, etc.) and completes the flow
+    /// with [`OAuth2::exchange_manual_code`]. A login handler is still
+    /// mounted if `login` is `Some`.
+    ///
+    /// PKCE is always disabled for this flow: `exchange_manual_code` has no
+    /// cookie round-trip to read a code verifier back from, so a verifier
+    /// generated by `get_redirect` could never reach the token exchange, and
+    /// the provider would reject it with `invalid_grant`. PKCE + OOB isn't
+    /// supported yet.
+    pub fn custom_oob(
+        adapter: A,
+        callback: C,
+        config: OAuthConfig,
+        login: Option<(&str, Vec<String>)>,
+    ) -> impl Fairing {
+        let mut routes = Vec::new();
+
+        let mut login_scopes = vec![];
+        if let Some((uri, scopes)) = login {
+            routes.push(Route::new(Method::Get, uri, login_handler::<A, C>));
+            login_scopes = scopes;
+        }
+
         let oauth2 = Self {
             adapter,
             callback,
             config,
             login_scopes,
+            pkce_enabled: false,
+            logout_redirect: None,
         };
 
         AdHoc::on_attach("OAuth Mount", |rocket| {
@@ -199,17 +573,46 @@ impl<A: Adapter, C: Callback> OAuth2<A, C> {
 
     /// Prepare an authentication redirect. This sets a state cookie and returns
     /// a `Redirect` to the provider's authorization page.
+    ///
+    /// If PKCE is enabled, a code verifier is generated and stored in its own
+    /// private cookie ([`PKCE_VERIFIER_COOKIE_NAME`]), and the corresponding
+    /// `code_challenge`/`code_challenge_method=S256` parameters are added to
+    /// the authorization URI per RFC 7636 §4.3.
     pub fn get_redirect(
         &self,
         cookies: &mut Cookies<'_>,
         scopes: &[&str],
     ) -> Result<Redirect, A::Error> {
-        let (uri, state) = self.adapter.authorization_uri(&self.config, scopes)?;
+        let pkce_verifier = if self.pkce_enabled {
+            Some(generate_pkce_verifier())
+        } else {
+            None
+        };
+        let pkce_challenge = pkce_verifier
+            .as_ref()
+            .map(|verifier| pkce_code_challenge(verifier));
+
+        let (uri, state) = self.adapter.authorization_uri(
+            &self.config,
+            scopes,
+            pkce_challenge.as_ref().map(|c| (c.as_str(), "S256")),
+        )?;
+
         cookies.add_private(
-            Cookie::build(STATE_COOKIE_NAME, state.clone())
+            Cookie::build(STATE_COOKIE_NAME, state)
                 .same_site(SameSite::Lax)
                 .finish(),
         );
+        // Kept in its own cookie (rather than packed alongside `state` behind a
+        // delimiter) since `state` is adapter-generated and not guaranteed to
+        // avoid any particular character.
+        if let Some(verifier) = pkce_verifier {
+            cookies.add_private(
+                Cookie::build(PKCE_VERIFIER_COOKIE_NAME, verifier)
+                    .same_site(SameSite::Lax)
+                    .finish(),
+            );
+        }
         Ok(Redirect::to(uri))
     }
 
@@ -219,20 +622,158 @@ impl<A: Adapter, C: Callback> OAuth2<A, C> {
         self.adapter.exchange_code(&self.config, TokenRequest::RefreshToken(refresh_token.to_string()))
     }
 
-    // TODO: Decide if BadRequest is the appropriate error code.
-    // TODO: What do providers do if they *reject* the authorization?
+    /// Query the authorization server about the current state of `token`
+    /// (RFC 7662). Useful for validating opaque tokens without making a full
+    /// userinfo request.
+    pub fn introspect(&self, token: &str) -> Result<IntrospectionResponse, A::Error> {
+        self.adapter.introspect(&self.config, token)
+    }
+
+    /// Revoke an access or refresh token so it can no longer be used (RFC 7009).
+    pub fn revoke(&self, token: RevocableToken) -> Result<(), A::Error> {
+        self.adapter.revoke(&self.config, token)
+    }
+
+    /// Return a valid access token for `key` from `store`, refreshing it
+    /// first if at most [`DEFAULT_REFRESH_THRESHOLD_SECS`] seconds remain
+    /// until expiry. A cached entry with no recorded expiry (see
+    /// [`StoredToken::expires_at`]) is returned as-is, since there is
+    /// nothing to compare against; see [`OAuth2::force_refresh_token`] to
+    /// refresh unconditionally. See [`OAuth2::get_valid_token_with_threshold`]
+    /// to use a different threshold.
+    pub fn get_valid_token<K, S>(
+        &self,
+        store: &S,
+        key: K,
+    ) -> Result<TokenResponse, TokenStoreError<A::Error>>
+    where
+        K: Clone,
+        S: TokenStore<K>,
+    {
+        self.get_valid_token_with_threshold(
+            store,
+            key,
+            Duration::from_secs(DEFAULT_REFRESH_THRESHOLD_SECS),
+        )
+    }
+
+    /// Like [`OAuth2::get_valid_token`], but with an explicit refresh
+    /// threshold instead of [`DEFAULT_REFRESH_THRESHOLD_SECS`].
+    pub fn get_valid_token_with_threshold<K, S>(
+        &self,
+        store: &S,
+        key: K,
+        threshold: Duration,
+    ) -> Result<TokenResponse, TokenStoreError<A::Error>>
+    where
+        K: Clone,
+        S: TokenStore<K>,
+    {
+        self.get_valid_token_impl(store, key, threshold, false)
+    }
+
+    /// Unconditionally refresh the token cached for `key`, ignoring
+    /// `expires_at` entirely. Useful when a cached entry has no recorded
+    /// expiry (see [`StoredToken::expires_at`]) and the resource server has
+    /// rejected it, or any other case where the cache is known to be stale
+    /// despite looking valid.
+    pub fn force_refresh_token<K, S>(
+        &self,
+        store: &S,
+        key: K,
+    ) -> Result<TokenResponse, TokenStoreError<A::Error>>
+    where
+        K: Clone,
+        S: TokenStore<K>,
+    {
+        self.get_valid_token_impl(store, key, Duration::from_secs(0), true)
+    }
+
+    fn get_valid_token_impl<K, S>(
+        &self,
+        store: &S,
+        key: K,
+        threshold: Duration,
+        force: bool,
+    ) -> Result<TokenResponse, TokenStoreError<A::Error>>
+    where
+        K: Clone,
+        S: TokenStore<K>,
+    {
+        // The get-then-maybe-refresh-then-put sequence below must run as one
+        // unit per key: without the lock, two concurrent calls could both
+        // read the same stale token, both refresh with the same (often
+        // single-use) refresh token, and race on which result ends up stored.
+        store.with_lock(&key, || {
+            let stored = store.get(&key).ok_or(TokenStoreError::NotFound)?;
+
+            if !needs_refresh(stored.expires_at, threshold, force) {
+                return Ok(stored.token);
+            }
+
+            let refresh_token = stored
+                .token
+                .refresh_token
+                .clone()
+                .ok_or(TokenStoreError::NoRefreshToken)?;
+
+            let mut new_token = self
+                .refresh(&refresh_token)
+                .map_err(TokenStoreError::Adapter)?;
+
+            carry_forward_refresh_token(&mut new_token, stored.token.refresh_token);
+
+            let expires_at = new_token
+                .expires_in
+                .map(|secs| SystemTime::now() + Duration::from_secs(secs.max(0) as u64));
+
+            store.put(
+                key.clone(),
+                StoredToken {
+                    token: new_token.clone(),
+                    expires_at,
+                },
+            );
+
+            Ok(new_token)
+        })
+    }
+
+    /// Handle the logout route mounted by [`OAuth2::custom_with_logout`]:
+    /// best-effort revoke any refresh token stashed in the
+    /// [`REFRESH_TOKEN_COOKIE_NAME`] cookie, remove it, and redirect to the
+    /// configured post-logout URI.
+    fn logout(&self, cookies: &mut Cookies<'_>) -> Redirect {
+        if let Some(cookie) = cookies.get_private(REFRESH_TOKEN_COOKIE_NAME) {
+            let refresh_token = cookie.value().to_string();
+            cookies.remove(cookie);
+            let token = RevocableToken::RefreshToken(refresh_token);
+            if let Err(e) = self.adapter.revoke(&self.config, token) {
+                log::error!("Failed to revoke refresh token during logout: {:?}", e);
+            }
+        }
+        Redirect::to(self.logout_redirect.clone().unwrap_or_else(|| "/".to_string()))
+    }
+
     /// Handle the redirect callback, delegating to the adapter and callback to
     /// perform the token exchange and application-specific actions.
     fn handle<'r>(&self, request: &'r Request<'_>, _data: Data) -> handler::Outcome<'r> {
         // Parse the query data.
         let query = request.uri().query().into_outcome(Status::BadRequest)?;
 
+        // RFC 6749 §4.1.2 and §4.1.2.1: the provider redirects back with either
+        // `code` (and optionally nonstandard `scope`) on success, or `error` (and
+        // optionally `error_description`/`error_uri`) on failure. `state` is
+        // present in both cases.
         #[derive(FromForm)]
         struct CallbackQuery {
-            code: String,
-            state: String,
+            code: Option<String>,
             // Nonstandard (but see below)
-            scope: Option<String>
+            scope: Option<String>,
+            error: Option<String>,
+            error_description: Option<String>,
+            error_uri: Option<String>,
+            state: String,
         }
 
         let params = match CallbackQuery::from_form(&mut FormItems::from(query), false) {
@@ -240,7 +781,9 @@ impl<A: Adapter, C: Callback> OAuth2<A, C> {
             Err(_) => return handler::Outcome::failure(Status::BadRequest),
         };
 
-        {
+        // The PKCE verifier, if any, is kept in its own cookie alongside the
+        // state cookie; see `get_redirect`.
+        let pkce_verifier = {
             // Verify that the given state is the same one in the cookie.
             // Begin a new scope so that cookies is not kept around too long.
             let mut cookies = request.guard::<Cookies<'_>>().expect("request cookies");
@@ -250,20 +793,32 @@ impl<A: Adapter, C: Callback> OAuth2<A, C> {
                 }
                 _ => return handler::Outcome::failure(Status::BadRequest),
             }
+            cookies.get_private(PKCE_VERIFIER_COOKIE_NAME).map(|cookie| {
+                let verifier = cookie.value().to_string();
+                cookies.remove(cookie);
+                verifier
+            })
+        };
+
+        if let Some(error) = params.error {
+            let token_error = TokenError {
+                error,
+                error_description: params.error_description,
+                error_uri: params.error_uri,
+            };
+            return match self.callback.callback_error(request, token_error) {
+                Ok(responder) => handler::Outcome::from(request, responder),
+                Err(status) => handler::Outcome::failure(status),
+            };
         }
 
-        // Have the adapter perform the token exchange.
-        let token = match self.adapter.exchange_code(&self.config, TokenRequest::AuthorizationCode(params.code)) {
-            Ok(mut token) => {
-                // Some providers (at least Strava) provide 'scope' in the callback
-                // parameters instead of the token response as the RFC prescribes.
-                // Therefore the 'scope' from the callback params is used as a fallback
-                // if the token response does not specify one.
-                if token.scope.is_none() {
-                    token.scope = params.scope;
-                }
-                token
-            },
+        let code = match params.code {
+            Some(code) => code,
+            None => return handler::Outcome::failure(Status::BadRequest),
+        };
+
+        let token = match self.exchange_and_fallback_scope(code, pkce_verifier, params.scope) {
+            Ok(token) => token,
             Err(e) => {
                 log::error!("Token exchange failed: {:?}", e);
                 return handler::Outcome::failure(Status::BadRequest);
@@ -274,6 +829,45 @@ impl<A: Adapter, C: Callback> OAuth2<A, C> {
         let responder = self.callback.callback(request, token);
         handler::Outcome::from(request, responder)
     }
+
+    /// Perform the authorization code exchange and apply the callback-param
+    /// scope fallback, shared by the mounted redirect route and
+    /// [`OAuth2::exchange_manual_code`].
+    fn exchange_and_fallback_scope(
+        &self,
+        code: String,
+        pkce_verifier: Option<String>,
+        fallback_scope: Option<String>,
+    ) -> Result<TokenResponse, A::Error> {
+        let token_request = TokenRequest::AuthorizationCode { code, pkce_verifier };
+        let mut token = self.adapter.exchange_code(&self.config, token_request)?;
+        // Some providers (at least Strava) provide 'scope' in the callback
+        // parameters instead of the token response as the RFC prescribes.
+        // Therefore the 'scope' from the callback params is used as a fallback
+        // if the token response does not specify one.
+        if token.scope.is_none() {
+            token.scope = fallback_scope;
+        }
+        Ok(token)
+    }
+
+    /// Perform the authorization code exchange directly, without a live
+    /// browser redirect. Intended for out-of-band flows
+    /// ([`OOB_REDIRECT_URI`] or a provider-specific variant) where the user
+    /// pastes the authorization code into the application (a CLI or desktop
+    /// client) instead of the provider redirecting to a hosted callback.
+    ///
+    /// `state`, if the application tracked one from [`OAuth2::get_redirect`],
+    /// is accepted for the caller's own bookkeeping; unlike `handle`, it is
+    /// not verified here, since OOB flows have no cookie round-trip to check
+    /// it against.
+    pub fn exchange_manual_code(
+        &self,
+        code: &str,
+        _state: Option<&str>,
+    ) -> Result<TokenResponse, A::Error> {
+        self.exchange_and_fallback_scope(code.to_string(), None, None)
+    }
 }
 
 // These cannot be closures becuase of the lifetime parameter.
@@ -306,3 +900,110 @@ fn login_handler<'r, A: Adapter, C: Callback>(
     let scopes: Vec<_> = oauth.login_scopes.iter().map(String::as_str).collect();
     handler::Outcome::from(request, oauth.get_redirect(&mut cookies, &scopes))
 }
+
+/// Handles a logout route, performing a best-effort revocation and redirect
+fn logout_handler<'r, A: Adapter, C: Callback>(
+    request: &'r Request<'_>,
+    _data: Data,
+) -> handler::Outcome<'r> {
+    let oauth = match request.guard::<State<'_, OAuth2<A, C>>>() {
+        Outcome::Success(oauth) => oauth,
+        Outcome::Failure(_) => return handler::Outcome::failure(Status::InternalServerError),
+        Outcome::Forward(()) => unreachable!(),
+    };
+    let mut cookies = request.guard::<Cookies<'_>>().expect("request cookies");
+    handler::Outcome::from(request, oauth.logout(&mut cookies))
+}
+
+#[cfg(test)]
+mod pkce_tests {
+    use super::{generate_pkce_verifier, pkce_code_challenge, PKCE_VERIFIER_CHARSET, PKCE_VERIFIER_LEN};
+
+    #[test]
+    fn verifier_has_expected_length() {
+        let verifier = generate_pkce_verifier();
+        assert_eq!(verifier.len(), PKCE_VERIFIER_LEN);
+    }
+
+    #[test]
+    fn verifier_uses_only_allowed_characters() {
+        let verifier = generate_pkce_verifier();
+        assert!(verifier.bytes().all(|b| PKCE_VERIFIER_CHARSET.contains(&b)));
+    }
+
+    #[test]
+    fn verifiers_are_not_constant() {
+        // Not a proof of randomness, but catches a verifier generator that
+        // always returns the same string.
+        assert_ne!(generate_pkce_verifier(), generate_pkce_verifier());
+    }
+
+    #[test]
+    fn challenge_matches_rfc7636_appendix_b_vector() {
+        // RFC 7636 Appendix B's example verifier/challenge pair.
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = pkce_code_challenge(verifier);
+        assert_eq!(challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+    }
+}
+
+#[cfg(test)]
+mod get_valid_token_tests {
+    use super::{carry_forward_refresh_token, needs_refresh, TokenResponse};
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime};
+
+    fn token_response(refresh_token: Option<&str>) -> TokenResponse {
+        TokenResponse {
+            access_token: "access".to_string(),
+            token_type: "Bearer".to_string(),
+            expires_in: None,
+            refresh_token: refresh_token.map(str::to_string),
+            scope: None,
+            extras: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn no_expiry_never_needs_refresh() {
+        assert!(!needs_refresh(None, Duration::from_secs(60), false));
+    }
+
+    #[test]
+    fn already_expired_needs_refresh() {
+        let expires_at = SystemTime::now() - Duration::from_secs(1);
+        assert!(needs_refresh(Some(expires_at), Duration::from_secs(60), false));
+    }
+
+    #[test]
+    fn exactly_at_threshold_needs_refresh() {
+        let expires_at = SystemTime::now() + Duration::from_secs(60);
+        assert!(needs_refresh(Some(expires_at), Duration::from_secs(60), false));
+    }
+
+    #[test]
+    fn comfortably_outside_threshold_does_not_need_refresh() {
+        let expires_at = SystemTime::now() + Duration::from_secs(3600);
+        assert!(!needs_refresh(Some(expires_at), Duration::from_secs(60), false));
+    }
+
+    #[test]
+    fn force_always_needs_refresh() {
+        let expires_at = SystemTime::now() + Duration::from_secs(3600);
+        assert!(needs_refresh(Some(expires_at), Duration::from_secs(60), true));
+    }
+
+    #[test]
+    fn refresh_token_is_carried_forward_when_omitted() {
+        let mut new_token = token_response(None);
+        carry_forward_refresh_token(&mut new_token, Some("previous".to_string()));
+        assert_eq!(new_token.refresh_token.as_deref(), Some("previous"));
+    }
+
+    #[test]
+    fn refresh_token_is_kept_when_provider_rotates_it() {
+        let mut new_token = token_response(Some("rotated"));
+        carry_forward_refresh_token(&mut new_token, Some("previous".to_string()));
+        assert_eq!(new_token.refresh_token.as_deref(), Some("rotated"));
+    }
+}